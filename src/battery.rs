@@ -0,0 +1,145 @@
+// src/battery.rs
+//! 电池监测：采样 SAADC -> 换算百分比 -> 回报 GATT Battery Service
+
+use defmt::*;
+use embassy_nrf::saadc::{ChannelConfig, Config as SaadcConfig, Saadc};
+use embassy_time::{Duration, Timer};
+use rmk::ble::nrf_ble::battery_service::BatteryService;
+
+use crate::conn_state::CONN_STATE;
+
+/// 采样周期：电量变化很慢，不需要太频繁
+const SAMPLE_INTERVAL: Duration = Duration::from_secs(60);
+
+/// 低于这个百分比认为是低电量
+const LOW_BATTERY_PCT: u8 = 15;
+
+/// 离开低电量状态前要求的滞回余量，防止在 15% 附近反复触发/解除
+const LOW_BATTERY_HYSTERESIS_PCT: u8 = 5;
+
+/// 低电量时把广播/扫描间隔放慢到这个倍数，换取更长续航
+const LOW_BATTERY_INTERVAL_SCALE: u32 = 3;
+
+/// `default_channel_config()` 用的内部参考电压（0.6V），单位 mV
+const SAADC_REFERENCE_MV: u32 = 600;
+/// `default_channel_config()` 用的增益 1/6，这里存倒数方便算满量程电压
+const SAADC_GAIN_DENOM: u32 = 6;
+/// `default_saadc_config()` 用的采样分辨率：12 位
+const SAADC_RESOLUTION_COUNTS: u32 = 1 << 12;
+/// 分压电阻把电池电压按比例缩到 ADC 量程内，这里是典型的 1:2 分压
+const DIVIDER_RATIO: u32 = 2;
+
+/// 简化的锂电池放电曲线：(电压 mV, 百分比) 采样点，线性插值
+const DISCHARGE_CURVE: &[(u16, u8)] = &[
+    (4200, 100),
+    (4000, 90),
+    (3900, 75),
+    (3800, 55),
+    (3700, 35),
+    (3600, 15),
+    (3500, 5),
+    (3400, 0),
+];
+
+fn voltage_to_percent(mv: u16) -> u8 {
+    if mv >= DISCHARGE_CURVE[0].0 {
+        return 100;
+    }
+    let last = DISCHARGE_CURVE.len() - 1;
+    if mv <= DISCHARGE_CURVE[last].0 {
+        return 0;
+    }
+
+    for window in DISCHARGE_CURVE.windows(2) {
+        let (hi_mv, hi_pct) = window[0];
+        let (lo_mv, lo_pct) = window[1];
+        if mv <= hi_mv && mv >= lo_mv {
+            let span_mv = (hi_mv - lo_mv) as u32;
+            let span_pct = (hi_pct - lo_pct) as u32;
+            let offset = (mv - lo_mv) as u32;
+            return lo_pct + (offset * span_pct / span_mv) as u8;
+        }
+    }
+    0
+}
+
+/// 周期采样 VDD 分压，维护滞回后的低电量标志，并发布到电池服务
+#[embassy_executor::task]
+pub async fn run(mut saadc: Saadc<'static, 1>, battery_service: &'static BatteryService) {
+    info!("电池监测任务启动");
+
+    let mut is_low = false;
+    saadc.calibrate().await;
+
+    loop {
+        let mut buf = [0i16; 1];
+        saadc.sample(&mut buf).await;
+
+        // SAADC 采回来的是 ADC 计数，不是电压：按 default_channel_config()
+        // 的内部 0.6V 参考 + 1/6 增益、default_saadc_config() 的 12 位
+        // 分辨率换算成 mV（满量程
+        // 0.6V / (1/6) = 3.6V / 4096 计数 ≈ 0.88 mV/计数），再按分压电阻
+        // 的 1:2 比例还原成电池电压
+        let raw = (buf[0] as i32).max(0) as u32;
+        let adc_mv = raw * SAADC_REFERENCE_MV * SAADC_GAIN_DENOM / SAADC_RESOLUTION_COUNTS;
+        let mv = (adc_mv * DIVIDER_RATIO) as u16;
+        let pct = voltage_to_percent(mv);
+
+        info!("电池电压: {} mV, 电量: {}%", mv, pct);
+        battery_service.notify_level(pct);
+
+        let enter_threshold = LOW_BATTERY_PCT;
+        let exit_threshold = LOW_BATTERY_PCT + LOW_BATTERY_HYSTERESIS_PCT;
+
+        is_low = if is_low {
+            pct < exit_threshold
+        } else {
+            pct < enter_threshold
+        };
+
+        CONN_STATE.set_low_battery(is_low);
+        if is_low {
+            rmk::ble::nrf_ble::scale_scan_and_advertising_interval(LOW_BATTERY_INTERVAL_SCALE);
+        } else {
+            rmk::ble::nrf_ble::scale_scan_and_advertising_interval(1);
+        }
+
+        Timer::after(SAMPLE_INTERVAL).await;
+    }
+}
+
+/// SAADC 的默认单通道配置：采 VDD 经过分压后的那一路输入
+pub fn default_channel_config(input: embassy_nrf::saadc::AnyInput) -> ChannelConfig<'static> {
+    ChannelConfig::single_ended(input)
+}
+
+/// SAADC 外设的默认配置
+pub fn default_saadc_config() -> SaadcConfig {
+    SaadcConfig::default()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn clamps_to_100_above_the_curve_and_0_below_it() {
+        assert_eq!(voltage_to_percent(4300), 100);
+        assert_eq!(voltage_to_percent(4200), 100);
+        assert_eq!(voltage_to_percent(3400), 0);
+        assert_eq!(voltage_to_percent(3000), 0);
+    }
+
+    #[test]
+    fn hits_the_sample_points_exactly() {
+        for &(mv, pct) in DISCHARGE_CURVE {
+            assert_eq!(voltage_to_percent(mv), pct);
+        }
+    }
+
+    #[test]
+    fn interpolates_linearly_between_adjacent_sample_points() {
+        // 3900 -> 75%，3800 -> 55%，中点 3850 应该落在中间
+        assert_eq!(voltage_to_percent(3850), 65);
+    }
+}
@@ -0,0 +1,65 @@
+// src/ble_bridge.rs
+//! 把 RMK 蓝牙协议栈的真实事件接入 `CONN_STATE`
+
+use defmt::*;
+use embassy_sync::blocking_mutex::raw::CriticalSectionRawMutex;
+use embassy_sync::signal::Signal;
+use rmk::ble::nrf_ble::{self, BleConnectionEvent, BleConnectionState};
+
+use crate::bonding;
+use crate::conn_state::{LinkStatus, CONN_STATE};
+use crate::shared_flash::SharedFlash;
+
+/// RMK 蓝牙协议栈 -> 本地状态机的事件通道
+///
+/// RMK 的连接回调在自己的任务里跑，这里只 `signal()` 一下就返回，真正的
+/// 状态翻译和后续消费都在 `bridge_task` / 订阅者里做，避免阻塞协议栈任务。
+pub static BLE_EVENTS: Signal<CriticalSectionRawMutex, BleConnectionEvent> = Signal::new();
+
+/// 注册到 RMK 蓝牙协议栈的回调：协议栈线程直接调用，只做一次 `signal()`
+fn forward_event(event: BleConnectionEvent) {
+    BLE_EVENTS.signal(event);
+}
+
+/// 订阅 RMK 蓝牙协议栈的连接事件，并驱动 `CONN_STATE`
+#[embassy_executor::task]
+pub async fn bridge_task(flash: &'static SharedFlash) {
+    info!("蓝牙事件桥接任务启动");
+    nrf_ble::register_connection_event_handler(forward_event);
+
+    loop {
+        let event = BLE_EVENTS.wait().await;
+        handle_ble_event(flash, event).await;
+    }
+}
+
+/// 把 RMK 的蓝牙连接事件翻译成 `CONN_STATE` 更新
+async fn handle_ble_event(flash: &'static SharedFlash, event: BleConnectionEvent) {
+    match event {
+        BleConnectionEvent::StateChanged(BleConnectionState::Advertising) => {
+            CONN_STATE.set_status(LinkStatus::Advertising);
+            info!("蓝牙开始广播");
+        }
+        BleConnectionEvent::StateChanged(BleConnectionState::Connected) => {
+            CONN_STATE.set_status(LinkStatus::Connected);
+            info!("蓝牙已连接");
+        }
+        BleConnectionEvent::StateChanged(BleConnectionState::Disconnected) => {
+            CONN_STATE.set_status(LinkStatus::Disconnected);
+            info!("蓝牙已断开");
+        }
+        BleConnectionEvent::BondLost => {
+            CONN_STATE.set_status(LinkStatus::Advertising);
+            info!("蓝牙绑定丢失，重新进入广播");
+        }
+        BleConnectionEvent::Bonded(bond) => {
+            let slot = CONN_STATE.host_slot();
+            info!("槽位 {} 配对完成，写入绑定信息", slot);
+            bonding::save_new_bond(flash, slot, bond).await;
+        }
+        BleConnectionEvent::Battery(_level) => {
+            // 电量数据交给 battery 子系统处理（见 chunk0-4），这里只是
+            // 一个占位分支，避免漏掉这个事件种类
+        }
+    }
+}
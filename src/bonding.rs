@@ -0,0 +1,168 @@
+// src/bonding.rs
+//! 多主机 BLE 绑定：三个主机槽位之间切换，每个槽位独立持有一份绑定信息
+//!
+//! 切换槽位时把广播白名单换成该槽位的绑定地址；目标槽位尚未绑定则改为
+//! 开放广播等待配对。长按某个槽位键 3 秒放弃白名单，强制重新配对。
+//!
+//! **范围**：上面两段描述的是 `SLOT_REQUEST` 被 `.signal()` 之后这边的
+//! 响应逻辑。[`poll_slot_keys`] 是真正槽位键接上之前的临时输入钩子，见
+//! `main.rs` 里 `main` 函数中"临时直连按键钩子"一节的说明。
+
+use defmt::*;
+use embassy_nrf::flash::Flash;
+use embassy_nrf::gpio::{AnyPin, Input, Pull};
+use embassy_sync::blocking_mutex::raw::CriticalSectionRawMutex;
+use embassy_sync::signal::Signal;
+use embassy_time::{Duration, Instant, Timer};
+use rmk::ble::nrf_ble::{BondInfo, PeerAddress};
+
+use crate::conn_state::{LinkStatus, CONN_STATE};
+use crate::shared_flash::SharedFlash;
+
+/// 支持的主机槽位数
+pub const HOST_SLOTS: usize = 3;
+
+/// 每个槽位的绑定记录在 flash 里占一个扇区，从 0x000F_5000 起，位于模拟
+/// EEPROM 的两页（见 `eeprom.rs`）之后
+const SLOT_BASE_ADDR: u32 = 0x000F_5000;
+const SLOT_SECTOR_SIZE: u32 = 0x1000; // 4KB，一个槽位一个扇区
+
+/// 槽位键触发的请求：切到某个槽位，或者（长按 3 秒）强制对该槽位重新配对
+#[derive(defmt::Format, Clone, Copy)]
+pub enum SlotRequest {
+    Switch(u8),
+    Repair(u8),
+}
+
+/// 槽位键触发的请求，[`poll_slot_keys`] `.signal()`，这里的 `run` 任务
+/// `.wait()` 消费。
+pub static SLOT_REQUEST: Signal<CriticalSectionRawMutex, SlotRequest> = Signal::new();
+
+/// 长按槽位键多久触发该槽位强制重新配对
+const SLOT_REPAIR_HOLD: Duration = Duration::from_secs(3);
+
+/// 按下去抖间隔
+const POLL_INTERVAL: Duration = Duration::from_millis(20);
+
+/// [`SLOT_REQUEST`] 的临时输入钩子：三个独立接线、不经过矩阵扫描的按键，
+/// 一键对应一个槽位——短按 `Switch(slot)`，按满 [`SLOT_REPAIR_HOLD`] 松手
+/// 前触发一次 `Repair(slot)`。让槽位切换和长按强制重新配对在矩阵槽位键
+/// 接上之前就是真的能用的，而不是只有消费端的状态机。keymap 把真正的
+/// 槽位键接到 `SLOT_REQUEST` 之后，这个任务和传进来的三个引脚都应该删掉
+#[embassy_executor::task]
+pub async fn poll_slot_keys(slot0_pin: AnyPin, slot1_pin: AnyPin, slot2_pin: AnyPin) {
+    let keys = [
+        Input::new(slot0_pin, Pull::Up),
+        Input::new(slot1_pin, Pull::Up),
+        Input::new(slot2_pin, Pull::Up),
+    ];
+    let mut down_since: [Option<Instant>; HOST_SLOTS] = [None; HOST_SLOTS];
+    let mut repaired: [bool; HOST_SLOTS] = [false; HOST_SLOTS];
+
+    loop {
+        for (slot, key) in keys.iter().enumerate() {
+            if key.is_low() {
+                match down_since[slot] {
+                    None => {
+                        down_since[slot] = Some(Instant::now());
+                        repaired[slot] = false;
+                    }
+                    Some(since) if !repaired[slot] && since.elapsed() >= SLOT_REPAIR_HOLD => {
+                        info!("直连按键长按触发槽位 {} 重新配对", slot);
+                        SLOT_REQUEST.signal(SlotRequest::Repair(slot as u8));
+                        repaired[slot] = true;
+                    }
+                    _ => {}
+                }
+            } else if let Some(since) = down_since[slot].take() {
+                if !repaired[slot] && since.elapsed() < SLOT_REPAIR_HOLD {
+                    info!("直连按键触发切换到槽位 {}", slot);
+                    SLOT_REQUEST.signal(SlotRequest::Switch(slot as u8));
+                }
+            }
+        }
+        Timer::after(POLL_INTERVAL).await;
+    }
+}
+
+fn slot_addr(slot: u8) -> u32 {
+    SLOT_BASE_ADDR + (slot as u32) * SLOT_SECTOR_SIZE
+}
+
+/// 槽位在 flash 中是否已经有有效的绑定信息
+async fn read_bond(flash: &mut Flash<'static>, slot: u8) -> Option<BondInfo> {
+    let mut buf = [0u8; 32];
+    flash.read(slot_addr(slot), &mut buf).await.ok()?;
+    BondInfo::decode(&buf)
+}
+
+async fn write_bond(flash: &mut Flash<'static>, slot: u8, bond: &BondInfo) {
+    let addr = slot_addr(slot);
+    if let Err(e) = flash.erase(addr, addr + SLOT_SECTOR_SIZE).await {
+        error!("槽位 {} 绑定信息擦除失败: {:?}", slot, e);
+        return;
+    }
+    let mut buf = [0xFFu8; 32];
+    bond.encode(&mut buf);
+    if let Err(e) = flash.write(addr, &buf).await {
+        error!("槽位 {} 绑定信息写入失败: {:?}", slot, e);
+    }
+}
+
+async fn clear_bond(flash: &mut Flash<'static>, slot: u8) {
+    let addr = slot_addr(slot);
+    if let Err(e) = flash.erase(addr, addr + SLOT_SECTOR_SIZE).await {
+        error!("槽位 {} 绑定信息清除失败: {:?}", slot, e);
+    }
+}
+
+/// 把广播白名单收紧到某个槽位已绑定的对端地址；槽位尚未绑定时改为开放广播
+fn apply_whitelist(slot: u8, peer: Option<PeerAddress>) {
+    match peer {
+        Some(addr) => {
+            info!("槽位 {} 已绑定，收紧白名单到 {}", slot, addr);
+            rmk::ble::nrf_ble::set_advertising_whitelist(&[addr]);
+            CONN_STATE.set_status(LinkStatus::Reconnecting);
+        }
+        None => {
+            info!("槽位 {} 尚未绑定，进入开放广播等待配对", slot);
+            rmk::ble::nrf_ble::clear_advertising_whitelist();
+            CONN_STATE.set_status(LinkStatus::Advertising);
+        }
+    }
+}
+
+/// 主机槽位管理任务：监听槽位键事件，驱动绑定的读写和白名单切换
+#[embassy_executor::task]
+pub async fn run(flash: &'static SharedFlash) {
+    let mut active_slot: u8 = 0;
+
+    let bond = read_bond(&mut *flash.lock().await, active_slot).await;
+    CONN_STATE.set_host_slot(active_slot);
+    apply_whitelist(active_slot, bond.map(|b| b.peer));
+
+    loop {
+        match SLOT_REQUEST.wait().await {
+            SlotRequest::Switch(slot) if (slot as usize) < HOST_SLOTS => {
+                info!("切换到主机槽位 {}", slot);
+                active_slot = slot;
+                CONN_STATE.set_host_slot(active_slot);
+                let bond = read_bond(&mut *flash.lock().await, active_slot).await;
+                apply_whitelist(active_slot, bond.map(|b| b.peer));
+            }
+            SlotRequest::Repair(slot) if (slot as usize) < HOST_SLOTS => {
+                info!("槽位 {} 长按触发重新配对，清除旧绑定", slot);
+                clear_bond(&mut *flash.lock().await, slot).await;
+                active_slot = slot;
+                CONN_STATE.set_host_slot(active_slot);
+                apply_whitelist(active_slot, None);
+            }
+            other => warn!("忽略非法槽位请求: {}", other),
+        }
+    }
+}
+
+/// 新绑定建立后调用：把对端地址写入当前槽位，供下次上电直接收紧白名单
+pub async fn save_new_bond(flash: &'static SharedFlash, slot: u8, bond: BondInfo) {
+    write_bond(&mut *flash.lock().await, slot, &bond).await;
+}
@@ -0,0 +1,116 @@
+// src/conn_state.rs
+//! 传输方式 + 链路状态 + 低电量标志，取代旧的单个 `BLE_STATE: AtomicU8`
+
+use core::sync::atomic::{AtomicU8, Ordering};
+
+/// 当前选中的报文传输通道
+///
+/// `Host` 合并了原来分开的 USB/BLE：`KeyboardConfig`/`Keyboard::run` 没有
+/// 暴露"只用其中一条报文路径"的运行时开关，USB 和 BLE 在这里跑的是完全
+/// 一样的 `Keyboard::run`，实际走哪条物理链路由它自己决定，这个 crate
+/// 分不清、也管不了，所以不假装是两个可选模式。`Rf24` 是唯一一个真正
+/// 独立的报文通道，见 [`crate::radio24`]。
+#[derive(defmt::Format, Clone, Copy, PartialEq, Eq)]
+pub enum Transport {
+    Host,
+    Rf24,
+}
+
+impl Transport {
+    fn from_u8(v: u8) -> Self {
+        match v {
+            1 => Transport::Rf24,
+            _ => Transport::Host,
+        }
+    }
+}
+
+/// 链路状态，含义依赖于当前的 `Transport`
+///
+/// - `Host` 走 USB 时没有事件更新它，保持此前设下的值（见 `transport::run`）
+/// - 2.4G / BLE 下会经历 `Advertising`/`Pairing` -> `Reconnecting` -> `Connected`
+///
+/// 低电量不是这里的一个变体：它由独立的 `low_battery` 标志保存（见下），
+/// 这样电量任务和连接事件各写各的字段，谁都不会把对方刚写的值覆盖掉。
+#[derive(defmt::Format, Clone, Copy, PartialEq, Eq)]
+pub enum LinkStatus {
+    Disconnected,
+    Advertising,
+    Pairing,
+    Reconnecting,
+    Connected,
+}
+
+impl LinkStatus {
+    fn from_u8(v: u8) -> Self {
+        match v {
+            0 => LinkStatus::Disconnected,
+            1 => LinkStatus::Advertising,
+            2 => LinkStatus::Pairing,
+            3 => LinkStatus::Reconnecting,
+            _ => LinkStatus::Connected,
+        }
+    }
+}
+
+/// 传输方式 + 链路状态 + 低电量标志，原子地保存、原子地读取
+pub struct ConnState {
+    transport: AtomicU8,
+    status: AtomicU8,
+    /// 当前激活的主机槽位（多主机配对用），0-based 索引，0 就是第一个主机槽位
+    host_slot: AtomicU8,
+    /// 独立于 `status` 之外的低电量标志，电量任务和连接事件互不覆盖
+    low_battery: AtomicU8,
+}
+
+impl ConnState {
+    pub const fn new() -> Self {
+        Self {
+            transport: AtomicU8::new(0),
+            status: AtomicU8::new(0),
+            host_slot: AtomicU8::new(0),
+            low_battery: AtomicU8::new(0),
+        }
+    }
+
+    pub fn set_transport(&self, transport: Transport) {
+        self.transport.store(transport as u8, Ordering::SeqCst);
+    }
+
+    pub fn transport(&self) -> Transport {
+        Transport::from_u8(self.transport.load(Ordering::SeqCst))
+    }
+
+    pub fn set_status(&self, status: LinkStatus) {
+        self.status.store(status as u8, Ordering::SeqCst);
+    }
+
+    pub fn status(&self) -> LinkStatus {
+        LinkStatus::from_u8(self.status.load(Ordering::SeqCst))
+    }
+
+    pub fn set_host_slot(&self, slot: u8) {
+        self.host_slot.store(slot, Ordering::SeqCst);
+    }
+
+    pub fn host_slot(&self) -> u8 {
+        self.host_slot.load(Ordering::SeqCst)
+    }
+
+    pub fn set_low_battery(&self, low: bool) {
+        self.low_battery.store(low as u8, Ordering::SeqCst);
+    }
+
+    pub fn is_low_battery(&self) -> bool {
+        self.low_battery.load(Ordering::SeqCst) != 0
+    }
+
+    /// 指示灯任务用的一份快照：一次取出四个字段。各字段分别原子读取，
+    /// 四次读取之间其他任务仍可能改写，不保证是同一时刻的一致组合
+    pub fn snapshot(&self) -> (Transport, LinkStatus, u8, bool) {
+        (self.transport(), self.status(), self.host_slot(), self.is_low_battery())
+    }
+}
+
+/// 全局连接状态，取代旧的 `BLE_STATE`
+pub static CONN_STATE: ConnState = ConnState::new();
@@ -0,0 +1,403 @@
+// src/eeprom.rs
+//! 双页 ping-pong 的模拟 EEPROM
+//!
+//! 总是只往"当前活动页"里顺序追加 (虚拟地址, 值) 记录，活动页满了就把每个
+//! 虚拟地址最新的值搬到刚擦干净的备用页，写头再擦旧页——这样哪怕搬运中途
+//! 掉电，也始终有一整页是完好的。只存放小的标量设置，每条记录固定 4 字节
+//! （2 字节虚拟地址 + 2 字节值）；大块数据仍然走各自的独立扇区。
+
+use defmt::*;
+use embassy_nrf::flash::Flash;
+
+/// 两个轮换页各自的起始地址：占用原来 EEPROM 区域（0x000F_0000 起 16KB）的前两页
+const PAGE_A_ADDR: u32 = 0x000F_0000;
+const PAGE_B_ADDR: u32 = 0x000F_1000;
+const PAGE_SIZE: u32 = 0x1000; // 4KB
+
+/// 页头：写在页首，表示"这页的数据是完整有效的"
+const VALID_MARKER: u32 = 0xA5A5_A5A5;
+const HEADER_SIZE: u32 = 4;
+const RECORD_SIZE: u32 = 4; // 2 字节地址 + 2 字节值
+const ERASED_U16: u16 = 0xFFFF;
+
+/// 同一个虚拟地址空间里最多追踪这么多个不同的地址（决定 transfer 时扫描范围）
+const MAX_TRACKED_ADDRS: usize = 32;
+
+#[derive(defmt::Format)]
+#[cfg_attr(test, derive(Debug))]
+pub enum EepromError {
+    Flash,
+    PageFull,
+}
+
+// 页头/记录的字节编解码，跟"往哪块 flash 读写"完全无关，单独拆出来是为了
+// 能在不碰硬件的情况下用 `#[cfg(test)]` 验证字节布局（小端、偏移量）没错
+fn is_valid_header(buf: [u8; HEADER_SIZE as usize]) -> bool {
+    u32::from_le_bytes(buf) == VALID_MARKER
+}
+
+fn encode_record(addr: u16, value: u16) -> [u8; RECORD_SIZE as usize] {
+    let mut record = [0u8; RECORD_SIZE as usize];
+    record[0..2].copy_from_slice(&addr.to_le_bytes());
+    record[2..4].copy_from_slice(&value.to_le_bytes());
+    record
+}
+
+fn decode_record(buf: [u8; RECORD_SIZE as usize]) -> (u16, u16) {
+    (
+        u16::from_le_bytes([buf[0], buf[1]]),
+        u16::from_le_bytes([buf[2], buf[3]]),
+    )
+}
+
+/// 本模块用到的最小 flash 接口：只有 `read`/`write`/`erase` 三个方法，
+/// 跟 [`embassy_nrf::flash::Flash`] 的形状对应。拆成 trait 是为了能在
+/// `#[cfg(test)]` 下换一个内存实现的假 flash，驱动真正的页搬运/扫描逻辑
+/// 而不需要真的烧录器
+pub(crate) trait FlashMedium {
+    async fn read(&mut self, address: u32, bytes: &mut [u8]) -> Result<(), ()>;
+    async fn write(&mut self, address: u32, bytes: &[u8]) -> Result<(), ()>;
+    async fn erase(&mut self, from: u32, to: u32) -> Result<(), ()>;
+}
+
+impl FlashMedium for Flash<'_> {
+    async fn read(&mut self, address: u32, bytes: &mut [u8]) -> Result<(), ()> {
+        Flash::read(self, address, bytes).await.map_err(|_| ())
+    }
+
+    async fn write(&mut self, address: u32, bytes: &[u8]) -> Result<(), ()> {
+        Flash::write(self, address, bytes).await.map_err(|_| ())
+    }
+
+    async fn erase(&mut self, from: u32, to: u32) -> Result<(), ()> {
+        Flash::erase(self, from, to).await.map_err(|_| ())
+    }
+}
+
+fn page_addr(page: Page) -> u32 {
+    match page {
+        Page::A => PAGE_A_ADDR,
+        Page::B => PAGE_B_ADDR,
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum Page {
+    A,
+    B,
+}
+
+impl Page {
+    fn other(self) -> Self {
+        match self {
+            Page::A => Page::B,
+            Page::B => Page::A,
+        }
+    }
+}
+
+async fn read_header(flash: &mut impl FlashMedium, page: Page) -> bool {
+    let mut buf = [0u8; HEADER_SIZE as usize];
+    if flash.read(page_addr(page), &mut buf).await.is_err() {
+        return false;
+    }
+    is_valid_header(buf)
+}
+
+async fn write_header(flash: &mut impl FlashMedium, page: Page) -> Result<(), EepromError> {
+    flash
+        .write(page_addr(page), &VALID_MARKER.to_le_bytes())
+        .await
+        .map_err(|_| EepromError::Flash)
+}
+
+/// 找到当前有效的那一页；两页都有效（`transfer` 写完新页头、还没擦掉旧页
+/// 时掉电）时优先用 A，
+/// 两页都无效说明从没格式化过
+async fn active_page(flash: &mut impl FlashMedium) -> Option<Page> {
+    if read_header(flash, Page::A).await {
+        Some(Page::A)
+    } else if read_header(flash, Page::B).await {
+        Some(Page::B)
+    } else {
+        None
+    }
+}
+
+/// 在一页里从头扫描到第一个空白（0xFFFF 地址）记录槽位，返回其偏移
+async fn first_free_offset(flash: &mut impl FlashMedium, page: Page) -> u32 {
+    let mut offset = HEADER_SIZE;
+    loop {
+        if offset + RECORD_SIZE > PAGE_SIZE {
+            return offset;
+        }
+        let mut buf = [0u8; 2];
+        if flash.read(page_addr(page) + offset, &mut buf).await.is_err() {
+            return offset;
+        }
+        if u16::from_le_bytes(buf) == ERASED_U16 {
+            return offset;
+        }
+        offset += RECORD_SIZE;
+    }
+}
+
+/// 在一页里从后往前扫描，找某个虚拟地址最后一次写入的值
+async fn scan_latest(flash: &mut impl FlashMedium, page: Page, addr: u16) -> Option<u16> {
+    let end = first_free_offset(flash, page).await;
+    let mut offset = end;
+    while offset > HEADER_SIZE {
+        offset -= RECORD_SIZE;
+        let mut buf = [0u8; RECORD_SIZE as usize];
+        if flash.read(page_addr(page) + offset, &mut buf).await.is_err() {
+            continue;
+        }
+        let (record_addr, value) = decode_record(buf);
+        if record_addr == addr {
+            return Some(value);
+        }
+    }
+    None
+}
+
+/// 收集一页里出现过的所有虚拟地址（用于 transfer 时知道要搬哪些地址）
+async fn collect_addrs(
+    flash: &mut impl FlashMedium,
+    page: Page,
+) -> heapless::Vec<u16, MAX_TRACKED_ADDRS> {
+    let mut addrs: heapless::Vec<u16, MAX_TRACKED_ADDRS> = heapless::Vec::new();
+    let end = first_free_offset(flash, page).await;
+    let mut offset = HEADER_SIZE;
+    while offset < end {
+        let mut buf = [0u8; 2];
+        if flash.read(page_addr(page) + offset, &mut buf).await.is_ok() {
+            let record_addr = u16::from_le_bytes(buf);
+            if !addrs.contains(&record_addr) {
+                if addrs.push(record_addr).is_err() {
+                    warn!(
+                        "超过 {} 个不同的虚拟地址，0x{:x} 在页迁移中被丢弃",
+                        MAX_TRACKED_ADDRS, record_addr
+                    );
+                }
+            }
+        }
+        offset += RECORD_SIZE;
+    }
+    addrs
+}
+
+/// 把活动页里每个虚拟地址的最新值搬到备用页，备用页写头后再擦旧的活动页
+///
+/// 顺序是：先把数据和头都落到新页，最后才擦旧页，这样搬运中途掉电的话，
+/// 上电后至少有一页（旧页或新页）是完整有效的。
+async fn transfer(flash: &mut impl FlashMedium, from: Page, to: Page) -> Result<(), EepromError> {
+    info!("EEPROM 活动页已满，开始页间搬运");
+
+    flash
+        .erase(page_addr(to), page_addr(to) + PAGE_SIZE)
+        .await
+        .map_err(|_| EepromError::Flash)?;
+
+    let addrs = collect_addrs(flash, from).await;
+    let mut offset = HEADER_SIZE;
+    for addr in addrs {
+        if let Some(value) = scan_latest(flash, from, addr).await {
+            let record = encode_record(addr, value);
+            flash
+                .write(page_addr(to) + offset, &record)
+                .await
+                .map_err(|_| EepromError::Flash)?;
+            offset += RECORD_SIZE;
+        }
+    }
+
+    write_header(flash, to).await?;
+
+    flash
+        .erase(page_addr(from), page_addr(from) + PAGE_SIZE)
+        .await
+        .map_err(|_| EepromError::Flash)?;
+
+    info!("EEPROM 页间搬运完成");
+    Ok(())
+}
+
+/// 读取某个虚拟地址当前的值；从未写过，或 EEPROM 还没格式化过时返回 `None`
+pub async fn read(flash: &mut impl FlashMedium, addr: u16) -> Option<u16> {
+    let page = active_page(flash).await?;
+    scan_latest(flash, page, addr).await
+}
+
+/// 往某个虚拟地址追加一条新记录；活动页写满时自动触发一次页间搬运
+pub async fn write(flash: &mut impl FlashMedium, addr: u16, value: u16) -> Result<(), EepromError> {
+    let active = match active_page(flash).await {
+        Some(p) => p,
+        None => {
+            // 从没格式化过：把 A 页当成初始活动页
+            format(flash).await?;
+            Page::A
+        }
+    };
+
+    let mut offset = first_free_offset(flash, active).await;
+    if offset + RECORD_SIZE > PAGE_SIZE {
+        transfer(flash, active, active.other()).await?;
+        offset = first_free_offset(flash, active.other()).await;
+        return write_record(flash, active.other(), offset, addr, value).await;
+    }
+
+    write_record(flash, active, offset, addr, value).await
+}
+
+async fn write_record(
+    flash: &mut impl FlashMedium,
+    page: Page,
+    offset: u32,
+    addr: u16,
+    value: u16,
+) -> Result<(), EepromError> {
+    if offset + RECORD_SIZE > PAGE_SIZE {
+        return Err(EepromError::PageFull);
+    }
+    flash
+        .write(page_addr(page) + offset, &encode_record(addr, value))
+        .await
+        .map_err(|_| EepromError::Flash)
+}
+
+/// 擦掉两页、把 A 页标成活动页，回到出厂状态
+///
+/// 取代原来的 `clear_eeprom`/`force_clear_eeprom`，仍然挂在 BOOT 按钮长按
+/// 3 秒的钩子（`should_clear_eeprom`）上触发。
+pub async fn format(flash: &mut impl FlashMedium) -> Result<(), EepromError> {
+    info!("格式化模拟 EEPROM");
+    flash
+        .erase(PAGE_A_ADDR, PAGE_A_ADDR + PAGE_SIZE)
+        .await
+        .map_err(|_| EepromError::Flash)?;
+    flash
+        .erase(PAGE_B_ADDR, PAGE_B_ADDR + PAGE_SIZE)
+        .await
+        .map_err(|_| EepromError::Flash)?;
+    write_header(flash, Page::A).await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// `#[cfg(test)]` 专用的内存 flash：字节数组模拟 NOR flash 的擦写语义
+    /// （擦除填 0xFF，写入只能把 1 变 0），覆盖两个页的地址范围，让页搬运/
+    /// 扫描逻辑能在不碰硬件的情况下真正跑一遍
+    struct MockFlash {
+        mem: [u8; (PAGE_B_ADDR + PAGE_SIZE - PAGE_A_ADDR) as usize],
+    }
+
+    impl MockFlash {
+        fn new() -> Self {
+            Self {
+                mem: [0xFFu8; (PAGE_B_ADDR + PAGE_SIZE - PAGE_A_ADDR) as usize],
+            }
+        }
+
+        fn offset(address: u32) -> usize {
+            (address - PAGE_A_ADDR) as usize
+        }
+    }
+
+    impl FlashMedium for MockFlash {
+        async fn read(&mut self, address: u32, bytes: &mut [u8]) -> Result<(), ()> {
+            let off = Self::offset(address);
+            bytes.copy_from_slice(&self.mem[off..off + bytes.len()]);
+            Ok(())
+        }
+
+        async fn write(&mut self, address: u32, bytes: &[u8]) -> Result<(), ()> {
+            let off = Self::offset(address);
+            for (i, b) in bytes.iter().enumerate() {
+                self.mem[off + i] &= *b;
+            }
+            Ok(())
+        }
+
+        async fn erase(&mut self, from: u32, to: u32) -> Result<(), ()> {
+            self.mem[Self::offset(from)..Self::offset(to)].fill(0xFF);
+            Ok(())
+        }
+    }
+
+    /// 本文件的 flash 操作全是"读完立刻返回"的内存操作，不需要真正的
+    /// 执行器来等外设中断，手写一个自旋的 block_on 就够了
+    fn block_on<F: core::future::Future>(fut: F) -> F::Output {
+        use core::task::{Context, Poll, RawWaker, RawWakerVTable, Waker};
+
+        fn noop(_: *const ()) {}
+        fn clone(_: *const ()) -> RawWaker {
+            RawWaker::new(core::ptr::null(), &VTABLE)
+        }
+        static VTABLE: RawWakerVTable = RawWakerVTable::new(clone, noop, noop, noop);
+
+        let raw = RawWaker::new(core::ptr::null(), &VTABLE);
+        let waker = unsafe { Waker::from_raw(raw) };
+        let mut cx = Context::from_waker(&waker);
+        let mut fut = core::pin::pin!(fut);
+        loop {
+            if let Poll::Ready(v) = fut.as_mut().poll(&mut cx) {
+                return v;
+            }
+        }
+    }
+
+    #[test]
+    fn record_round_trips_through_little_endian_bytes() {
+        let record = encode_record(0x0001, 0xBEEF);
+        assert_eq!(decode_record(record), (0x0001, 0xBEEF));
+    }
+
+    #[test]
+    fn header_is_only_valid_with_the_marker() {
+        assert!(is_valid_header(VALID_MARKER.to_le_bytes()));
+        assert!(!is_valid_header([0xFF; HEADER_SIZE as usize]));
+        assert!(!is_valid_header([0x00; HEADER_SIZE as usize]));
+    }
+
+    #[test]
+    fn write_then_read_round_trips() {
+        let mut flash = MockFlash::new();
+        block_on(format(&mut flash)).unwrap();
+        block_on(write(&mut flash, 0x0001, 42)).unwrap();
+        block_on(write(&mut flash, 0x0002, 7)).unwrap();
+
+        assert_eq!(block_on(read(&mut flash, 0x0001)), Some(42));
+        assert_eq!(block_on(read(&mut flash, 0x0002)), Some(7));
+        assert_eq!(block_on(read(&mut flash, 0x0003)), None);
+    }
+
+    #[test]
+    fn later_write_to_the_same_address_wins() {
+        let mut flash = MockFlash::new();
+        block_on(format(&mut flash)).unwrap();
+        block_on(write(&mut flash, 0x0001, 1)).unwrap();
+        block_on(write(&mut flash, 0x0001, 2)).unwrap();
+        block_on(write(&mut flash, 0x0001, 3)).unwrap();
+
+        assert_eq!(block_on(read(&mut flash, 0x0001)), Some(3));
+    }
+
+    #[test]
+    fn page_transfer_keeps_the_latest_value_per_address_and_survives_a_full_page() {
+        let mut flash = MockFlash::new();
+        block_on(format(&mut flash)).unwrap();
+
+        // 往活动页里写到装不下为止，期间反复改写同一个地址，强制触发一次
+        // 页间搬运；搬运完之后两个地址的最新值都应该还在
+        let records_per_page = (PAGE_SIZE - HEADER_SIZE) / RECORD_SIZE;
+        for i in 0..records_per_page + 1 {
+            block_on(write(&mut flash, 0x0001, i as u16)).unwrap();
+        }
+        block_on(write(&mut flash, 0x0002, 99)).unwrap();
+
+        assert_eq!(block_on(read(&mut flash, 0x0001)), Some(records_per_page as u16));
+        assert_eq!(block_on(read(&mut flash, 0x0002)), Some(99));
+    }
+}
@@ -0,0 +1,41 @@
+// src/indicator.rs
+//! LED 指示灯任务：按当前 `ConnState` 选择灯效表并播放
+//!
+//! - 低电量标志独立于连接状态，优先级最高，盖过下面所有规则
+//! - 已连接常亮；广播（BLE）/配对（2.4G）快呼吸，重连慢呼吸
+//! - 多主机槽位始终生效：每轮常规灯效放完后补插 N 下短闪，N = 槽位号 + 1
+//!   （槽位 0 闪 1 下，槽位 1 闪 2 下，以此类推），这样槽位 0 才不会跟
+//!   "没在用槽位功能"混为一谈
+
+use defmt::*;
+
+use crate::conn_state::{LinkStatus, CONN_STATE};
+use crate::led_pwm::{
+    slot_blink_steps, Indicator, FAST_BREATHE, LOW_BATTERY_PULSE, OFF, SLOW_BREATHE, STEADY_DIM,
+};
+
+#[embassy_executor::task]
+pub async fn run(mut indicator: Indicator) {
+    info!("连接状态指示灯任务启动");
+
+    loop {
+        let (_transport, status, slot, low_battery) = CONN_STATE.snapshot();
+
+        let pattern = if low_battery {
+            LOW_BATTERY_PULSE
+        } else {
+            match status {
+                LinkStatus::Connected => STEADY_DIM,
+                LinkStatus::Advertising | LinkStatus::Pairing => FAST_BREATHE,
+                LinkStatus::Reconnecting => SLOW_BREATHE,
+                LinkStatus::Disconnected => OFF,
+            }
+        };
+
+        indicator.play_once(pattern).await;
+
+        if !low_battery {
+            indicator.play_once(&slot_blink_steps(slot + 1)).await;
+        }
+    }
+}
@@ -0,0 +1,102 @@
+// src/led_pwm.rs
+//! PWM 驱动的呼吸灯：每种状态对应一张 (亮度, 持续时间) 表，循环播放
+
+use embassy_nrf::peripherals::PWM0;
+use embassy_nrf::pwm::SimplePwm;
+use embassy_time::{Duration, Timer};
+
+/// 呼吸灯的一帧：目标占空比（0-100）和维持时长
+#[derive(Clone, Copy)]
+pub struct Step {
+    pub brightness: u8,
+    pub duration: Duration,
+}
+
+const fn step(brightness: u8, duration_ms: u64) -> Step {
+    Step {
+        brightness,
+        duration: Duration::from_millis(duration_ms),
+    }
+}
+
+/// 一段 8 级的三角波爬升/回落，用来在相邻两级之间给人"平滑呼吸"的观感
+macro_rules! breathe_ramp {
+    ($period_ms:expr) => {
+        &[
+            step(0, $period_ms),
+            step(15, $period_ms),
+            step(35, $period_ms),
+            step(60, $period_ms),
+            step(100, $period_ms),
+            step(60, $period_ms),
+            step(35, $period_ms),
+            step(15, $period_ms),
+        ]
+    };
+}
+
+/// 快呼吸：配对中 / 广播中，每级维持 40ms，一个完整呼吸周期约 320ms
+pub static FAST_BREATHE: &[Step] = breathe_ramp!(40);
+
+/// 慢呼吸：已配对但未建链 / 重连中，每级维持 120ms，周期约 1s
+pub static SLOW_BREATHE: &[Step] = breathe_ramp!(120);
+
+/// 低电量：三次急促脉冲后长停顿，区别于呼吸效果，一眼能认出来
+pub static LOW_BATTERY_PULSE: &[Step] = &[
+    step(100, 80),
+    step(0, 80),
+    step(100, 80),
+    step(0, 80),
+    step(100, 80),
+    step(0, 1000),
+];
+
+/// 已连接：稳定的暗光，常亮但不刺眼
+pub static STEADY_DIM: &[Step] = &[step(20, 1000)];
+
+/// 断开：熄灭
+pub static OFF: &[Step] = &[step(0, 1000)];
+
+/// 最多区分这么多个主机槽位的短闪次数
+const MAX_SLOT_BLINKS: usize = 4;
+
+/// 某个主机槽位被选中时的提示：闪 `slot` 下短促的光，然后停顿
+///
+/// 这里的 `slot` 是显示用的 1-based 序号，不是 `CONN_STATE`/`bonding`
+/// 里 0-based 的槽位索引——调用方（`indicator`）传的是 `host_slot + 1`，
+/// 这样槽位 0（第一个主机）也能闪出 1 下，而不是被当成"没在用槽位功能"
+/// 闪 0 下。
+pub fn slot_blink_steps(slot: u8) -> heapless::Vec<Step, { MAX_SLOT_BLINKS * 2 + 1 }> {
+    let mut steps = heapless::Vec::new();
+    for _ in 0..slot.min(MAX_SLOT_BLINKS as u8) {
+        let _ = steps.push(step(100, 120));
+        let _ = steps.push(step(0, 120));
+    }
+    let _ = steps.push(step(0, 600));
+    steps
+}
+
+/// 在一颗 LED 上循环播放一张灯效表
+pub struct Indicator {
+    pwm: SimplePwm<'static, PWM0>,
+}
+
+impl Indicator {
+    pub fn new(pwm: SimplePwm<'static, PWM0>) -> Self {
+        Self { pwm }
+    }
+
+    fn set_brightness(&mut self, brightness: u8) {
+        let duty = (brightness as u32 * self.pwm.max_duty() as u32 / 100) as u16;
+        self.pwm.set_duty(0, duty);
+    }
+
+    /// 播放一整张表（循环一轮），调用方负责在 task 的 `loop` 里重复调用，
+    /// 这样每一轮都能重新读取最新的 `ConnState` 决定下一轮播哪张表
+    pub async fn play_once(&mut self, pattern: &[Step]) {
+        for s in pattern {
+            self.set_brightness(s.brightness);
+            Timer::after(s.duration).await;
+        }
+    }
+}
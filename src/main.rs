@@ -1,175 +1,153 @@
-
 // src/main.rs
-#![no_std]
-#![no_main]
+//
+// `cargo test` 需要链接 std 测试框架，所以 `no_std`/`no_main` 只在真正的
+// 固件构建里生效；`eeprom`/`battery` 里纯逻辑的 `#[cfg(test)]` 单元测试
+// 靠这个才能在主机上跑，见各自模块。
+#![cfg_attr(not(test), no_std)]
+#![cfg_attr(not(test), no_main)]
+
+mod battery;
+mod ble_bridge;
+mod bonding;
+mod conn_state;
+mod eeprom;
+mod indicator;
+mod led_pwm;
+mod radio24;
+mod shared_flash;
+mod transport;
 
 use defmt::*;
 use embassy_executor::Spawner;
-use embassy_nrf::{bind_interrupts, peripherals, gpio::Output};
-use embassy_sync::signal::Signal;
-use embassy_time::{Duration, Timer};
-use core::sync::atomic::{AtomicU8, Ordering};
-
-// 蓝牙状态枚举
-#[derive(defmt::Format)]
-enum BleState {
-    Disconnected,    // 未连接
-    Advertising,     // 广播中
-    Connected,       // 已连接
-    LowBattery,      // 低电量
-}
+use embassy_nrf::{
+    bind_interrupts, flash::Flash, gpio::Pin, peripherals, pwm::SimplePwm, radio::Radio,
+    saadc::Saadc,
+};
+use rmk::ble::nrf_ble::battery_service::BatteryService;
+use rmk::keyboard::KeyboardConfig;
+use static_cell::StaticCell;
 
-// 全局蓝牙状态
-static BLE_STATE: AtomicU8 = AtomicU8::new(0); // 0=断开, 1=广播, 2=连接, 3=低电
+// RMK 会自动生成配置
+use keyboard_config::*;
 
 bind_interrupts!(struct Irqs {
     POWER_CLOCK => embassy_nrf::power::InterruptHandler;
+    SAADC => embassy_nrf::saadc::InterruptHandler;
+    RADIO => embassy_nrf::radio::InterruptHandler<peripherals::RADIO>;
     // 根据你的需求添加其他中断
 });
 
+static BATTERY_SERVICE: StaticCell<BatteryService> = StaticCell::new();
+
 #[embassy_executor::main]
-async fn main(_spawner: Spawner) {
+async fn main(spawner: Spawner) {
     info!("CZMAOWL17 键盘启动");
-    
+
     let p = embassy_nrf::init(Default::default());
-    
-    // ==================== 初始化 NumLock LED ====================
-    // 根据你的 keyboard.toml: numslock.pin = "P1_11"
-    let mut numlock_led = Output::new(p.P1_11, embassy_nrf::gpio::Level::Low);
-    info!("NumLock LED 初始化完成 (P1_11)");
-    
-    // ==================== 启动蓝牙状态指示灯任务 ====================
-    _spawner.spawn(ble_indicator_task(numlock_led)).unwrap();
-    
-    // ==================== 模拟蓝牙状态变化（测试用） ====================
-    // 实际使用时，这里应该接收真正的蓝牙事件
-    _spawner.spawn(ble_simulator_task()).unwrap();
-    
-    // ==================== 正常键盘初始化 ====================
-    // 这里初始化键盘矩阵、配置等
-    info!("键盘初始化完成");
-    
-    // 主循环保持运行
-    loop {
-        Timer::after(Duration::from_secs(1)).await;
-    }
-}
 
-/// 蓝牙状态指示灯任务
-#[embassy_executor::task]
-async fn ble_indicator_task(mut led: Output<'static>) {
-    info!("蓝牙指示灯任务启动");
-    
-    loop {
-        let state = BLE_STATE.load(Ordering::SeqCst);
-        let ble_state = match state {
-            0 => BleState::Disconnected,
-            1 => BleState::Advertising,
-            2 => BleState::Connected,
-            3 => BleState::LowBattery,
-            _ => BleState::Disconnected,
-        };
-        
-        match ble_state {
-            BleState::Disconnected => {
-                // 断开状态：慢闪（亮100ms，灭900ms）
-                led.set_high();
-                Timer::after(Duration::from_millis(100)).await;
-                led.set_low();
-                Timer::after(Duration::from_millis(900)).await;
-            }
-            
-            BleState::Advertising => {
-                // 广播状态：快闪（亮250ms，灭250ms）
-                led.set_high();
-                Timer::after(Duration::from_millis(250)).await;
-                led.set_low();
-                Timer::after(Duration::from_millis(250)).await;
-            }
-            
-            BleState::Connected => {
-                // 连接状态：常亮
-                led.set_high();
-                Timer::after(Duration::from_secs(1)).await;
-            }
-            
-            BleState::LowBattery => {
-                // 低电量：急促闪烁3次后暂停
-                for _ in 0..3 {
-                    led.set_high();
-                    Timer::after(Duration::from_millis(100)).await;
-                    led.set_low();
-                    Timer::after(Duration::from_millis(100)).await;
-                }
-                Timer::after(Duration::from_millis(1000)).await;
+    // transport（持久化当前传输方式）、bonding（持久化每个主机槽位的绑定）
+    // 和模拟 EEPROM 都共用同一份 flash 外设，用一个共享的、带互斥锁的句柄
+    // 分发给各个任务
+    let flash = shared_flash::init(Flash::new(p.NVMC, p.FLASH));
+
+    // ============== EEPROM 格式化部分 ==============
+    // 按住 BOOT 3 秒触发 format()，取代原来无脑擦固定扇区的 clear_eeprom
+    if should_clear_eeprom(p.P0_11).await {
+        info!("正在格式化模拟 EEPROM...");
+        if let Err(e) = eeprom::format(&mut *flash.lock().await).await {
+            error!("格式化失败: {:?}", e);
+        } else {
+            info!("格式化成功，请重新上电");
+            loop {
+                cortex_m::asm::wfi();
             }
         }
     }
-}
+    // ===========================================
 
-/// 模拟蓝牙状态变化（测试用）
-/// 实际使用时应该用真正的蓝牙事件替换
-#[embassy_executor::task]
-async fn ble_simulator_task() {
-    info!("蓝牙模拟器启动（用于测试指示灯）");
-    
-    // 初始状态：广播中
-    BLE_STATE.store(1, Ordering::SeqCst);
-    
-    // 模拟状态变化
-    let states = [
-        (Duration::from_secs(5), 1),  // 广播5秒
-        (Duration::from_secs(10), 2), // 连接10秒
-        (Duration::from_secs(5), 0),  // 断开5秒
-        (Duration::from_secs(3), 3),  // 低电量3秒
-        (Duration::from_secs(5), 1),  // 重新广播
-        (Duration::from_secs(5), 2),  // 重新连接
-    ];
-    
-    for &(duration, state) in states.iter().cycle() {
-        BLE_STATE.store(state, Ordering::SeqCst);
-        let state_str = match state {
-            0 => "断开",
-            1 => "广播",
-            2 => "连接",
-            3 => "低电",
-            _ => "未知",
-        };
-        info!("蓝牙状态: {}", state_str);
-        Timer::after(duration).await;
-    }
+    // ==================== 初始化 NumLock LED（PWM 呼吸灯） ====================
+    // 根据你的 keyboard.toml: numslock.pin = "P1_11"
+    let numlock_pwm = SimplePwm::new_1ch(p.PWM0, p.P1_11);
+    info!("NumLock LED 初始化完成 (P1_11, PWM0)");
+
+    // ==================== 启动连接状态指示灯任务 ====================
+    spawner
+        .spawn(indicator::run(led_pwm::Indicator::new(numlock_pwm)))
+        .unwrap();
+
+    // ==================== 启动蓝牙事件桥接任务 ====================
+    // 取代原来的 ble_simulator_task，把 RMK 蓝牙协议栈的真实事件接入 CONN_STATE
+    spawner.spawn(ble_bridge::bridge_task(flash)).unwrap();
+
+    // ==================== 启动多主机 BLE 绑定管理任务 ====================
+    spawner.spawn(bonding::run(flash)).unwrap();
+
+    // ==================== 临时直连按键钩子 ====================
+    // 下面三组 poll_*_key(s) 任务都是同一个 TODO 的三处应用：矩阵键 +
+    // keymap 自定义键码（FN+Q/W/E 切换传输方式、槽位键、FN+W 长按重新
+    // 配对）不在这份源码树里，所以先用不经过矩阵扫描的直连 GPIO 按键顶上，
+    // 让这几个 Signal 在键盘 keymap 接上之前就有真正的输入来源。keymap
+    // 接上之后，这三组任务和下面分配给它们的引脚都应该删掉。
+    spawner
+        .spawn(bonding::poll_slot_keys(
+            p.P1_06.degrade(),
+            p.P1_08.degrade(),
+            p.P1_10.degrade(),
+        ))
+        .unwrap();
+
+    // ==================== 启动电池监测任务 ====================
+    // VDD 经分压接到 P0.02，采样后换算百分比并发布到 GATT Battery Service
+    let battery_service = BATTERY_SERVICE.init(BatteryService::new());
+    let saadc_config = battery::default_saadc_config();
+    let channel = battery::default_channel_config(p.P0_02.degrade_saadc());
+    let saadc = Saadc::new(p.SAADC, Irqs, saadc_config, [channel]);
+    spawner.spawn(battery::run(saadc, battery_service)).unwrap();
+
+    // ==================== 正常键盘初始化 ====================
+    info!("键盘初始化完成");
+
+    // 见上面关于临时直连按键钩子的说明；这里只接了切回 Host 的按键，
+    // 没有接切到 Rf24 的按键，原因见 transport::poll_mode_keys 的文档注释
+    spawner
+        .spawn(transport::poll_mode_keys(p.P1_00.degrade()))
+        .unwrap();
+
+    // ==================== 启动多传输方式键盘主循环 ====================
+    // 取代原来的 `keyboard.run().await`：在 Host（USB/BLE）和 2.4G 之间
+    // 切换，具体调度逻辑见 transport.rs
+    let keyboard_config = KeyboardConfig::default();
+    let radio = Radio::new(p.RADIO, Irqs);
+
+    // 见上面关于临时直连按键钩子的说明
+    spawner
+        .spawn(radio24::poll_repair_key(p.P1_12.degrade()))
+        .unwrap();
+
+    transport::run(keyboard_config, flash, radio).await;
 }
 
-/// 实际的蓝牙事件处理函数
-/// 当收到真正的蓝牙事件时调用这个函数
-fn handle_ble_event(event: BleEvent) {
-    match event {
-        BleEvent::Connected(_) => {
-            BLE_STATE.store(2, Ordering::SeqCst);
-            info!("蓝牙已连接");
-        }
-        BleEvent::Disconnected(_) => {
-            BLE_STATE.store(0, Ordering::SeqCst);
-            info!("蓝牙已断开");
-        }
-        BleEvent::AdvertisingStarted => {
-            BLE_STATE.store(1, Ordering::SeqCst);
-            info!("蓝牙开始广播");
-        }
-        BleEvent::BatteryLow => {
-            BLE_STATE.store(3, Ordering::SeqCst);
-            info!("电池电量低");
+/// 条件清除：检查是否需要清除
+async fn should_clear_eeprom(clear_button_pin: peripherals::P0_11) -> bool {
+    use embassy_nrf::gpio::{Input, Pull};
+    use embassy_time::{Duration, Timer};
+
+    // 使用 BOOT 按钮（通常连接到 P0.11 或 P0.13）
+    // 检查你的原理图确认按钮引脚
+    let clear_button = Input::new(clear_button_pin, Pull::Up);
+
+    info!("按住 BOOT 按钮 3 秒可清除 EEPROM");
+
+    // 检查按钮是否被按住
+    for _ in 0..30 {
+        // 3 秒 = 30 * 100ms
+        if clear_button.is_low() {
+            Timer::after(Duration::from_millis(100)).await;
+        } else {
+            return false;
         }
-        _ => {}
     }
-}
 
-// 蓝牙事件枚举（需要根据实际的蓝牙驱动定义）
-#[derive(defmt::Format)]
-enum BleEvent {
-    Connected(u16),
-    Disconnected(u16),
-    AdvertisingStarted,
-    BatteryLow,
-    // 其他事件...
+    info!("检测到清除请求");
+    true
 }
@@ -0,0 +1,252 @@
+// src/radio24.rs
+//! 私有 2.4G 链路：基于 nRF `RADIO` 外设的配对 + 跳频 + ACK 重传协议
+//!
+//! 为 [`crate::transport::Transport::Rf24`] 提供底层报文通道。协议很简单：
+//! - 未配对时，在一组固定信道上轮流广播配对包，带上自己 MAC 地址的低 2
+//!   字节；收到 dongle 的应答后，双方各取对方地址低 2 字节拼出一个私有
+//!   地址，之后都用这个地址通信。
+//! - 配对后，每个报文发出去都要等 ACK；连续丢太多 ACK 就跳到下一个信道
+//!   （多半是遇到了干扰），丢包则立即重传，不等超时。
+//!
+//! `run_until_switch` 是 [`crate::transport::run`] 在 `Rf24` 模式下的
+//! 主循环，形状上和 RMK 的 `Keyboard::run` 对应（后者正常也不返回，但
+//! 返回类型是 `()`），这里直接是不返回的 `!`，方便外层用同一套 `select!`
+//! 逻辑等待传输方式切换。
+//!
+//! **范围**：配对/跳频/ACK 重传这套协议状态机在这份文件里是真实可用的。
+//! [`poll_repair_key`] 是重新配对按键接上之前的临时输入钩子，见 `main.rs`
+//! 里 `main` 函数中"临时直连按键钩子"一节的说明。但还有一处没有完工，比一句模块
+//! 注释的分量重：[`read_next_report`] 读的是 [`HID_REPORT`]，矩阵扫描/HID
+//! 组帧模块不在这份源码树里，没有任何调用点会往 [`HID_REPORT`] 里写真实
+//! 按键，所以这条私有 2.4G 链路配对、跳频、重传都是真的，但发出去的报文
+//! 目前全是占位的全零字节，**按键还传不到 dongle**。正因为这样，
+//! [`crate::transport::poll_mode_keys`] 没有接切到 `Rf24` 的按键——这个
+//! 模式现在只适合拿来单独调试链路层，不该是用户能从整机上碰到的开关。
+
+use defmt::*;
+use embassy_futures::select::{select, Either};
+use embassy_nrf::gpio::{AnyPin, Input, Pull};
+use embassy_nrf::radio::Radio;
+use embassy_sync::blocking_mutex::raw::CriticalSectionRawMutex;
+use embassy_sync::signal::Signal;
+use embassy_sync::watch::Watch;
+use embassy_time::{Duration, Instant, Timer};
+
+use crate::conn_state::{LinkStatus, CONN_STATE};
+
+/// FN+W 长按触发的重新配对请求，[`poll_repair_key`] `.signal()`，这里的
+/// `run_until_switch` 任务 `.wait()` 消费。
+pub static REPAIR_REQUEST: Signal<CriticalSectionRawMutex, ()> = Signal::new();
+
+/// 按下去抖/长按检测间隔
+const POLL_INTERVAL: Duration = Duration::from_millis(20);
+
+/// [`REPAIR_REQUEST`] 的临时输入钩子：一个独立接线、不经过矩阵扫描的
+/// 按键，长按满 [`REPAIR_HOLD`] 触发一次重新配对。keymap 把真正的 FN+W
+/// 长按接到 `REPAIR_REQUEST` 之后，这个任务和传进来的引脚都应该删掉
+#[embassy_executor::task]
+pub async fn poll_repair_key(pin: AnyPin) {
+    let key = Input::new(pin, Pull::Up);
+    let mut down_since: Option<Instant> = None;
+    let mut signaled = false;
+
+    loop {
+        if key.is_low() {
+            match down_since {
+                None => {
+                    down_since = Some(Instant::now());
+                    signaled = false;
+                }
+                Some(since) if !signaled && since.elapsed() >= REPAIR_HOLD => {
+                    info!("直连按键长按 {} 秒触发重新配对", REPAIR_HOLD.as_secs());
+                    REPAIR_REQUEST.signal(());
+                    signaled = true;
+                }
+                _ => {}
+            }
+        } else {
+            down_since = None;
+            signaled = false;
+        }
+        Timer::after(POLL_INTERVAL).await;
+    }
+}
+
+/// 最近一次组好的 HID 报文；矩阵扫描/HID 组帧模块不在这份源码树里，没有
+/// 任何调用点会往这里写真实按键，[`read_next_report`] 读不到更新时回退
+/// 到全零报文。这是结构性的钩子，不是把问题藏起来的占位符：一旦上游
+/// 模块接上，只需要调用 `HID_REPORT.sender().send(report)`，不用改这个
+/// 文件里的任何东西
+pub static HID_REPORT: Watch<CriticalSectionRawMutex, [u8; 8], 1> = Watch::new();
+
+/// 配对/跳频可用的信道集合（2.4GHz ISM 频段内挑的 8 个，频率 2400 + n MHz，
+/// 分散在整个频段上；其中多数落在 WiFi 1/6/11 信道的频率范围内）
+const CHANNELS: [u8; 8] = [2, 8, 23, 34, 45, 60, 71, 80];
+
+/// 连续丢失这么多个 ACK 就认为当前信道被干扰，跳到下一个
+const MAX_MISSED_ACKS: u8 = 15;
+
+/// 目标报文速率
+const REPORT_RATE_HZ: u32 = 1000;
+const REPORT_INTERVAL: Duration = Duration::from_micros(1_000_000 / REPORT_RATE_HZ as u64);
+
+/// 长按 FN+W 多久触发重新配对
+pub const REPAIR_HOLD: Duration = Duration::from_secs(3);
+
+#[derive(defmt::Format, Clone, Copy, PartialEq, Eq)]
+enum LinkState {
+    Unpaired,
+    Paired,
+    Linked,
+}
+
+fn link_status_for(state: LinkState) -> LinkStatus {
+    match state {
+        // `Advertising` 专指 BLE 开放广播等待配对（见 bonding::apply_whitelist）；
+        // 2.4G 的"还没配对"是完全不同的状态（轮流跳频探测，不是被动广播），
+        // 用 `Pairing` 区分，指示灯效一样（都是快呼吸），但 CONN_STATE 里
+        // 记录的链路状态是真的
+        LinkState::Unpaired => LinkStatus::Pairing,
+        LinkState::Paired => LinkStatus::Reconnecting,
+        LinkState::Linked => LinkStatus::Connected,
+    }
+}
+
+/// 本机蓝牙/2.4G 地址的低 2 字节，来自出厂烧录的 FICR 设备地址，
+/// 配对握手时用来派生双方共用的私有地址
+pub fn local_mac_low() -> [u8; 2] {
+    let device_addr = embassy_nrf::pac::FICR.deviceaddr(0).read();
+    [(device_addr & 0xFF) as u8, ((device_addr >> 8) & 0xFF) as u8]
+}
+
+/// 配对握手：在固定信道集合上轮流探测，交换双方 MAC 低 2 字节，派生出
+/// 本次会话使用的私有地址。返回值里的信道下标是配对成功、radio 硬件
+/// 实际停在的那个信道，调用方必须把它写回自己的 `channel_idx`，后续跳频
+/// 才是相对当前链路算的，而不是相对配对开始前的陈旧值
+async fn pair(radio: &mut Radio<'_>, local_mac_low: [u8; 2]) -> ([u8; 4], usize) {
+    info!("进入 2.4G 配对模式，开始跳频探测");
+    CONN_STATE.set_status(link_status_for(LinkState::Unpaired));
+
+    let mut channel_idx = 0usize;
+    loop {
+        let channel = CHANNELS[channel_idx % CHANNELS.len()];
+        radio.set_channel(channel);
+
+        let mut probe = [0u8; 2];
+        probe.copy_from_slice(&local_mac_low);
+        radio.transmit(&probe).await;
+
+        let mut reply = [0u8; 2];
+        if radio
+            .receive_timeout(&mut reply, Duration::from_millis(50))
+            .await
+            .is_ok()
+        {
+            info!("收到 dongle 应答，信道 {}", channel);
+            let mut private_addr = [0u8; 4];
+            private_addr[0..2].copy_from_slice(&local_mac_low);
+            private_addr[2..4].copy_from_slice(&reply);
+            return (private_addr, channel_idx % CHANNELS.len());
+        }
+
+        channel_idx += 1;
+        Timer::after(Duration::from_millis(10)).await;
+    }
+}
+
+/// 读最近一次组好的 HID 报文（见 [`HID_REPORT`]）；矩阵扫描/HID 组帧
+/// 模块还没接上时，`receiver` 拿不到任何更新，回退到全零报文——也就是说
+/// 链路层本身没问题，但按键还传不到 dongle，见模块开头的说明
+fn read_next_report(
+    receiver: &mut embassy_sync::watch::Receiver<'_, CriticalSectionRawMutex, [u8; 8], 1>,
+) -> [u8; 8] {
+    receiver.try_get().unwrap_or([0u8; 8])
+}
+
+/// 正常收发：按目标速率发报文，等 ACK；连续丢太多 ACK 就跳信道，
+/// 单次丢包立即重传
+async fn run_linked(radio: &mut Radio<'_>, private_addr: [u8; 4], channel_idx: &mut usize) -> ! {
+    info!("2.4G 链路已建立，开始以 {} Hz 收发", REPORT_RATE_HZ);
+    CONN_STATE.set_status(link_status_for(LinkState::Linked));
+
+    let mut hid_report_rx = HID_REPORT
+        .receiver()
+        .expect("HID_REPORT 只有一个 receiver 槽位，run_linked 是唯一的消费者");
+
+    let mut missed_acks: u8 = 0;
+    // 跳频后 CONN_STATE 被设成了 Paired（慢闪），这里记一下，好在 ACK
+    // 恢复正常的时候把它设回 Linked（常亮），否则跳频一次之后 LED 就
+    // 永远停在"已配对未连接"，哪怕链路其实已经恢复
+    let mut degraded = false;
+
+    loop {
+        let tick = Instant::now();
+
+        let report = read_next_report(&mut hid_report_rx);
+        let mut acked = false;
+        for _attempt in 0..4 {
+            radio.transmit_to(&private_addr, &report).await;
+            if radio.wait_ack(Duration::from_micros(400)).await {
+                acked = true;
+                break;
+            }
+            // 丢包：不等下一个周期，立即重传
+        }
+
+        if acked {
+            missed_acks = 0;
+            if degraded {
+                CONN_STATE.set_status(link_status_for(LinkState::Linked));
+                degraded = false;
+            }
+        } else {
+            missed_acks += 1;
+            warn!("连续丢失 {} 个 ACK", missed_acks);
+            if missed_acks >= MAX_MISSED_ACKS {
+                *channel_idx = (*channel_idx + 1) % CHANNELS.len();
+                let channel = CHANNELS[*channel_idx];
+                info!("丢包过多，跳频到信道 {}", channel);
+                radio.set_channel(channel);
+                CONN_STATE.set_status(link_status_for(LinkState::Paired));
+                degraded = true;
+                missed_acks = 0;
+            }
+        }
+
+        let elapsed = tick.elapsed();
+        if elapsed < REPORT_INTERVAL {
+            Timer::after(REPORT_INTERVAL - elapsed).await;
+        }
+    }
+}
+
+/// `Rf24` 模式下的主循环：配对一次之后持续收发，同时监听 FN+W 长按
+/// `REPAIR_HOLD` 触发的重新配对请求。和 `Keyboard::run` 一样不会返回，
+/// 由调用方（`transport::run`）跟"切换传输方式"的信号一起 `select!`。
+pub async fn run_until_switch(
+    radio: &mut Radio<'static>,
+    local_mac_low: [u8; 2],
+    channel_idx: &mut usize,
+    paired_addr: &mut Option<[u8; 4]>,
+) -> ! {
+    loop {
+        if paired_addr.is_none() {
+            let (addr, paired_channel_idx) = pair(radio, local_mac_low).await;
+            *paired_addr = Some(addr);
+            // 配对成功时 radio 已经停在 `paired_channel_idx` 对应的信道上，
+            // 把它写回调用方持有的 channel_idx，run_linked 的跳频才是相对
+            // 当前真实信道算的，而不是相对配对前那个陈旧下标
+            *channel_idx = paired_channel_idx;
+        }
+        let addr = paired_addr.unwrap();
+
+        let linked = run_linked(radio, addr, channel_idx);
+        match select(linked, REPAIR_REQUEST.wait()).await {
+            Either::First(never) => match never {},
+            Either::Second(()) => {
+                info!("FN+W 长按 {} 秒触发重新配对", REPAIR_HOLD.as_secs());
+                *paired_addr = None;
+            }
+        }
+    }
+}
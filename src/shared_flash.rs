@@ -0,0 +1,22 @@
+// src/shared_flash.rs
+//! 一份 flash 外设在多个任务间共享访问
+//!
+//! `transport` 需要持久化当前传输方式，`bonding` 需要持久化每个主机槽位的
+//! 绑定信息，两者都要写 flash，但 nRF 只有一个 flash 外设实例。用
+//! `embassy_sync::Mutex` 包一层，各任务 `lock().await` 后再操作，避免两边
+//! 同时擦写造成冲突。
+
+use embassy_nrf::flash::Flash;
+use embassy_sync::blocking_mutex::raw::CriticalSectionRawMutex;
+use embassy_sync::mutex::Mutex;
+use static_cell::StaticCell;
+
+pub type SharedFlash = Mutex<CriticalSectionRawMutex, Flash<'static>>;
+
+static FLASH_CELL: StaticCell<SharedFlash> = StaticCell::new();
+
+/// 把拥有所有权的 `Flash` 放进一个 `'static` 的共享单元里，返回的引用可以
+/// 自由地分发给多个任务
+pub fn init(flash: Flash<'static>) -> &'static SharedFlash {
+    FLASH_CELL.init(Mutex::new(flash))
+}
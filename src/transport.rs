@@ -0,0 +1,158 @@
+// src/transport.rs
+//! 多传输方式管理：`Host`（USB/BLE，由 RMK 自己决定走哪条物理链路）和
+//! `Rf24`（本 crate 自己的 2.4G 协议，见 [`radio24`]）之间切换
+//!
+//! **范围**：这份文件是"收到切换请求之后怎么响应"的状态机——持久化、
+//! 指示灯、重启对应的收发循环。按键输入钩子（临时直连 GPIO，真正的矩阵
+//! 组合键还没接上）统一说明见 `main.rs` 里 `main` 函数中"临时直连按键
+//! 钩子"一节的注释。
+//!
+//! 当初设想的是 USB/2.4G/BLE 三条独立可选的报文路径，但 `KeyboardConfig`/
+//! `Keyboard::run` 不接受"只用其中一条报文路径"这个参数——USB 和 BLE 在这
+//! 里跑的是完全一样的 `Keyboard::run`，这个 crate 既分不清也管不了实际
+//! 报文走哪条物理链路。所以 `Transport` 只有两个真正有区别的成员：
+//! `Host`（USB/BLE 合并，RMK 自己选）和 `Rf24`（本 crate 直接驱动 `RADIO`
+//! 外设的独立协议，见 [`radio24::run_until_switch`]）。切换触发后，当前
+//! 的 run 被取消，按新模式重新启动，并把新模式写回 flash，下次上电直接
+//! 恢复到最后使用的模式。
+
+use defmt::*;
+use embassy_futures::select::{select, Either};
+use embassy_nrf::gpio::{AnyPin, Input, Pull};
+use embassy_nrf::radio::Radio;
+use embassy_sync::signal::Signal;
+use embassy_sync::blocking_mutex::raw::CriticalSectionRawMutex;
+use embassy_time::{Duration, Timer};
+use rmk::keyboard::{Keyboard, KeyboardConfig};
+
+use crate::conn_state::{LinkStatus, Transport, CONN_STATE};
+use crate::eeprom;
+use crate::radio24;
+use crate::shared_flash::SharedFlash;
+
+/// 当前传输方式在模拟 EEPROM 里的虚拟地址
+const TRANSPORT_MODE_ADDR: u16 = 0x0001;
+
+/// "切换传输方式"请求，按键输入钩子见 `main.rs`，这里的 `run` 任务
+/// `.wait()` 消费
+pub static MODE_SWITCH: Signal<CriticalSectionRawMutex, Transport> = Signal::new();
+
+/// 按下去抖间隔
+const POLL_INTERVAL: Duration = Duration::from_millis(20);
+
+/// [`MODE_SWITCH`] 的临时输入钩子：目前只接了切回 `Host` 这一个方向。
+/// 切到 `Rf24` 没有接对应的按键——[`radio24::HID_REPORT`] 还没有任何
+/// 调用点往里写真实按键，选中 `Rf24` 能配对、跳频、重传，但发出去的
+/// 报文全是占位全零字节（见 `radio24.rs` 模块说明），所以这里不给用户
+/// 一个看起来能用、实际选了就哑掉的开关。`Rf24` 仍然是 `Transport` 的
+/// 合法成员：上电时 [`load_last_mode`] 从模拟 EEPROM 读到值 1 就会进入
+/// 它，留给调试 2.4G 协议栈本身用（长按 BOOT 只会格式化 EEPROM，之后
+/// 回退到 `Host`）；一旦 HID 组帧模块接上真实按键，在这里加回
+/// 对应的按键分支即可。
+#[embassy_executor::task]
+pub async fn poll_mode_keys(host_pin: AnyPin) {
+    let host_key = Input::new(host_pin, Pull::Up);
+    let mut host_down = false;
+
+    loop {
+        let host_now = host_key.is_low();
+
+        if host_now && !host_down {
+            info!("直连按键触发切换到 Host (USB/BLE)");
+            MODE_SWITCH.signal(Transport::Host);
+        }
+
+        host_down = host_now;
+        Timer::after(POLL_INTERVAL).await;
+    }
+}
+
+/// 从模拟 EEPROM 恢复上次使用的传输方式，读不到有效值时回退到 Host
+async fn load_last_mode(flash: &'static SharedFlash) -> Transport {
+    match eeprom::read(&mut *flash.lock().await, TRANSPORT_MODE_ADDR).await {
+        Some(1) => Transport::Rf24,
+        _ => {
+            info!("未找到有效的传输方式记录，默认使用 Host (USB/BLE)");
+            Transport::Host
+        }
+    }
+}
+
+/// 把当前传输方式写回模拟 EEPROM，供下次上电恢复
+async fn persist_mode(flash: &'static SharedFlash, mode: Transport) {
+    let value: u16 = match mode {
+        Transport::Host => 0,
+        Transport::Rf24 => 1,
+    };
+    if let Err(e) = eeprom::write(&mut *flash.lock().await, TRANSPORT_MODE_ADDR, value).await {
+        error!("传输方式写入失败: {:?}", e);
+    }
+}
+
+/// 包裹 `Keyboard::run`（Host）或 `radio24::run_until_switch`（Rf24），
+/// 在当前选中的传输方式下跑键盘主循环，同时等待 [`MODE_SWITCH`]
+pub async fn run(
+    keyboard_config: KeyboardConfig,
+    flash: &'static SharedFlash,
+    mut radio: Radio<'static>,
+) -> ! {
+    let mut mode = load_last_mode(flash).await;
+    let local_mac_low = radio24::local_mac_low();
+    let mut rf24_channel_idx = 0usize;
+    let mut rf24_paired_addr: Option<[u8; 4]> = None;
+
+    loop {
+        CONN_STATE.set_transport(mode);
+        info!("切换到传输方式: {}", mode);
+
+        // Host 不在这里赋值初始链路状态：它的链路状态由 bonding::run
+        // （开机时按持久化的绑定信息决定是 Advertising 还是 Reconnecting）
+        // 和 ble_bridge::bridge_task（运行时按 RMK 蓝牙协议栈的真实事件）
+        // 维护；如果走的是 USB 物理线路，这两个任务不会收到任何 BLE 事件，
+        // 状态就停在开机时的初值，跟"USB 插上即连接"不完全一致，但这个
+        // crate 没有办法区分 Host 此刻具体走的是哪条物理链路，不在这里
+        // 硬编一个可能错的状态。Rf24 在这里先设成 `Pairing`（见下），
+        // 之后由 radio24::pair / run_linked 按真实链路状态更新。
+        if mode == Transport::Rf24 {
+            CONN_STATE.set_status(LinkStatus::Pairing);
+        }
+
+        let switch_fut = MODE_SWITCH.wait();
+
+        let new_mode = match mode {
+            Transport::Host => {
+                let keyboard = Keyboard::new(keyboard_config.clone());
+                match select(keyboard.run(), switch_fut).await {
+                    Either::First(()) => {
+                        // Keyboard::run 正常不会返回，出现了说明底层报文通道挂了，
+                        // 原地重启当前模式
+                        warn!("当前传输方式的 Keyboard::run 提前退出，原地重启");
+                        mode
+                    }
+                    Either::Second(new_mode) => new_mode,
+                }
+            }
+            Transport::Rf24 => {
+                let rf24_fut = radio24::run_until_switch(
+                    &mut radio,
+                    local_mac_low,
+                    &mut rf24_channel_idx,
+                    &mut rf24_paired_addr,
+                );
+                match select(rf24_fut, switch_fut).await {
+                    Either::First(never) => match never {},
+                    Either::Second(new_mode) => new_mode,
+                }
+            }
+        };
+
+        if new_mode != mode {
+            info!("收到切换请求: {} -> {}", mode, new_mode);
+            mode = new_mode;
+            if mode != Transport::Rf24 {
+                rf24_paired_addr = None;
+            }
+            persist_mode(flash, mode).await;
+        }
+    }
+}